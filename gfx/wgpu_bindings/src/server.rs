@@ -9,8 +9,35 @@ use crate::{
 
 use wgc::{gfx_select, id};
 
-use std::{error::Error, os::raw::c_char, ptr, slice};
+use std::{collections::HashMap, convert::TryInto, error::Error, os::raw::c_char, ptr, slice};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+/// The WebGPU error category a reported error belongs to.
+///
+/// This mirrors the `GPUError` subtypes defined by the spec: a `None` result
+/// indicates no error occurred, and the other variants tell `WebGPUParent.cpp`
+/// which of `GPUValidationError` / `GPUOutOfMemoryError` / `GPUInternalError`
+/// to construct for the content process, as required by `pushErrorScope` /
+/// `popErrorScope`.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ErrorBufferType {
+    None = 0,
+    Validation = 1,
+    OutOfMemory = 2,
+    Internal = 3,
+    DeviceLost = 4,
+}
+
+/// A classified error message: the text a caller would have gotten from
+/// `ErrorBuffer::init` before, paired with the `ErrorBufferType` it was
+/// classified as. Useful for callers that want to inspect or log a
+/// classification decision without going through the raw FFI buffer.
+pub struct ErrMsg {
+    pub message: String,
+    pub ty: ErrorBufferType,
+}
 
 /// A fixed-capacity, null-terminated error buffer owned by C++.
 ///
@@ -19,20 +46,54 @@ use std::sync::atomic::{AtomicU32, Ordering};
 /// `Result::Err` here, we convert the error to a string, copy as much of that
 /// string as fits into this buffer, and null-terminate it. The caller
 /// determines whether a error occurred by simply checking if there's any text
-/// before the first null byte.
+/// before the first null byte. `ty` is filled in alongside the string so the
+/// caller can pick the right `GPUError` subtype without having to sniff the
+/// message text itself.
 ///
 /// C++ callers of Rust functions that expect one of these structs can create a
 /// `mozilla::webgpu::ErrorBuffer` object, and call its `ToFFI` method to
 /// construct a value of this type, available to C++ as
 /// `mozilla::webgpu::ffi::WGPUErrorBuffer`.
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct ErrorBuffer {
     string: *mut c_char,
     capacity: usize,
+    ty: *mut ErrorBufferType,
 }
 
 impl ErrorBuffer {
-    /// Fill this buffer with the textual representation of `error`.
+    /// Fill this buffer with the textual representation of `error`, and
+    /// classify it into the `ErrorBufferType` that best matches it.
+    fn init(&mut self, error: impl Error + 'static) {
+        let ty = classify_error(&error, ErrorBufferType::Validation);
+        self.init_typed(ty, error);
+    }
+
+    /// Classify `error` by the same concrete-type rules `init` uses, without
+    /// writing it into this buffer. Lets a caller log or branch on the category before (or
+    /// instead of) reporting it to C++.
+    ///
+    /// Unlike `init`, anything that doesn't match a more specific category is
+    /// classified as `Internal` rather than `Validation`: callers of this
+    /// (currently just the `get_bind_group_layout` entry points) surface
+    /// errors that originate from pipeline/device state rather than from
+    /// arguments the content process passed in, so an unrecognized error is
+    /// more likely a driver/internal failure than a validation mistake.
+    fn classify(error: impl Error + 'static) -> ErrMsg {
+        let ty = classify_error(&error, ErrorBufferType::Internal);
+        let mut message = format!("{}", error);
+        let mut e = error.source();
+        while let Some(source) = e {
+            use std::fmt::Write;
+            write!(message, ", caused by: {}", source).unwrap();
+            e = source.source();
+        }
+        ErrMsg { message, ty }
+    }
+
+    /// Fill this buffer with the textual representation of `error`, tagging
+    /// it with an explicit `ErrorBufferType` rather than inferring one.
     ///
     /// If the error message is too long, truncate it as needed. In either case,
     /// the error message is always terminated by a zero byte.
@@ -42,7 +103,7 @@ impl ErrorBuffer {
     /// includes a zero byte (as Rust strings can), then the C++ code receiving
     /// this error message has no way to distinguish that from the terminating
     /// zero byte, and will see the message as shorter than it is.
-    fn init(&mut self, error: impl Error) {
+    fn init_typed(&mut self, ty: ErrorBufferType, error: impl Error) {
         use std::fmt::Write;
 
         let mut string = format!("{}", error);
@@ -52,10 +113,14 @@ impl ErrorBuffer {
             e = source.source();
         }
 
-        self.init_str(&string);
+        self.init_str_typed(ty, &string);
     }
 
     fn init_str(&mut self, message: &str) {
+        self.init_str_typed(ErrorBufferType::Validation, message);
+    }
+
+    fn init_str_typed(&mut self, ty: ErrorBufferType, message: &str) {
         assert_ne!(self.capacity, 0);
         let length = if message.len() >= self.capacity {
             log::warn!(
@@ -70,28 +135,104 @@ impl ErrorBuffer {
         unsafe {
             ptr::copy_nonoverlapping(message.as_ptr(), self.string as *mut u8, length);
             *self.string.add(length) = 0;
+            if !self.ty.is_null() {
+                *self.ty = ty;
+            }
         }
     }
 }
 
+/// Walk `error`'s source chain, `error` itself included, looking for a value
+/// of concrete type `T`, and return the first one found.
+fn downcast_chain<T: Error + 'static>(error: &(dyn Error + 'static)) -> Option<&T> {
+    let mut current = error;
+    loop {
+        if let Some(found) = current.downcast_ref::<T>() {
+            return Some(found);
+        }
+        current = current.source()?;
+    }
+}
+
+/// Infer the `ErrorBufferType` that best matches `error`, by matching the
+/// concrete wgpu-core error type and variant found in its source chain
+/// (`error` itself included) rather than sniffing display text. `default` is
+/// returned when nothing in the chain maps to a more specific category.
+fn classify_error<E: Error + 'static>(error: &E, default: ErrorBufferType) -> ErrorBufferType {
+    let error: &(dyn Error + 'static) = error;
+
+    if let Some(err) = downcast_chain::<wgc::device::DeviceError>(error) {
+        return match err {
+            wgc::device::DeviceError::OutOfMemory => ErrorBufferType::OutOfMemory,
+            wgc::device::DeviceError::Lost => ErrorBufferType::DeviceLost,
+            _ => ErrorBufferType::Internal,
+        };
+    }
+    if downcast_chain::<wgc::resource::BufferAccessError>(error).is_some() {
+        return ErrorBufferType::Validation;
+    }
+    if let Some(err) = downcast_chain::<wgc::resource::CreateBufferError>(error) {
+        return match err {
+            wgc::resource::CreateBufferError::AccessError(_) => ErrorBufferType::Validation,
+            _ => default,
+        };
+    }
+
+    default
+}
+
+/// The largest buffer size (in bytes) the server will accept for an
+/// allocation, mapped range, or copy.
+///
+/// Some drivers (e.g. mesa) misbehave when a size doesn't fit in a signed
+/// 32-bit integer, and an unbounded allocation request from a compromised
+/// content process is a stability/DoS risk. Rejecting oversized requests here
+/// keeps the device alive instead of letting the backend driver crash the
+/// GPU process.
+pub const MAX_BUFFER_SIZE: wgt::BufferAddress = 1 << 30;
+
+/// The largest width, height, or depth (in texels) the server will accept
+/// for a texture allocation.
+///
+/// Huge textures with any dimension beyond the 16-bit signed range can crash
+/// some backends, so requests past this are rejected before they ever reach
+/// `gfx_select!`'s real hal allocation.
+pub const MAX_TEXTURE_EXTENT: u32 = i16::MAX as u32;
+
+/// A swap chain's pool of presentable textures, and which one is currently
+/// acquired by the content process.
+struct SwapChainData {
+    /// The pre-allocated textures the content process cycles through.
+    textures: Vec<id::TextureId>,
+    /// The index into `textures` that `GetCurrentTexture` hands out;
+    /// advanced to the next texture in the pool on each present.
+    cursor: usize,
+}
+
 // hide wgc's global in private
-pub struct Global(wgc::hub::Global<IdentityRecyclerFactory>);
+pub struct Global {
+    hub: wgc::hub::Global<IdentityRecyclerFactory>,
+    swap_chains: Mutex<HashMap<SwapChainId, SwapChainData>>,
+}
 
 impl std::ops::Deref for Global {
     type Target = wgc::hub::Global<IdentityRecyclerFactory>;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.hub
     }
 }
 
 #[no_mangle]
 pub extern "C" fn wgpu_server_new(factory: IdentityRecyclerFactory) -> *mut Global {
     log::info!("Initializing WGPU server");
-    let global = Global(wgc::hub::Global::new(
-        "wgpu",
-        factory,
-        wgt::Backends::PRIMARY | wgt::Backends::GL,
-    ));
+    let global = Global {
+        hub: wgc::hub::Global::new(
+            "wgpu",
+            factory,
+            wgt::Backends::PRIMARY | wgt::Backends::GL,
+        ),
+        swap_chains: Mutex::new(HashMap::new()),
+    };
     Box::into_raw(Box::new(global))
 }
 
@@ -166,6 +307,67 @@ pub unsafe extern "C" fn wgpu_server_adapter_pack_info(
     *byte_buf = ByteBuf::from_vec(data);
 }
 
+/// An opaque identifier for a presentation surface, chosen by the content
+/// process the same way `SwapChainId` is.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SurfaceId(pub u64);
+
+/// The capabilities a surface supports when used as a swap chain's
+/// presentation target, serialized to the content process the same way
+/// `wgpu_server_adapter_pack_info` packs `AdapterInformation`.
+#[derive(serde::Serialize)]
+struct SurfaceCapabilities {
+    formats: Vec<wgt::TextureFormat>,
+    present_modes: Vec<wgt::PresentMode>,
+    alpha_modes: Vec<wgt::CompositeAlphaMode>,
+    usages: u32,
+}
+
+/// Report whether `adapter_id` can present to `surface_id`.
+///
+/// This crate's swap chains (see `wgpu_server_device_create_swap_chain`)
+/// render into a pool of plain textures rather than a native platform
+/// surface, so today every live adapter can present to every surface; this
+/// seam exists so a real hal surface integration can replace the result
+/// below (`true` for any live adapter, `false` otherwise) without changing
+/// the FFI contract.
+#[no_mangle]
+pub extern "C" fn wgpu_server_adapter_is_surface_supported(
+    global: &Global,
+    adapter_id: id::AdapterId,
+    _surface_id: SurfaceId,
+) -> bool {
+    gfx_select!(adapter_id => global.adapter_get_info(adapter_id)).is_ok()
+}
+
+/// Pack the formats, present modes, alpha modes, and usages `surface_id`
+/// supports when presented to by `adapter_id` into `byte_buf`, or a lone
+/// `0u64` if `adapter_id` is no longer valid.
+#[no_mangle]
+pub extern "C" fn wgpu_server_surface_get_capabilities(
+    global: &Global,
+    _surface_id: SurfaceId,
+    adapter_id: id::AdapterId,
+    byte_buf: &mut ByteBuf,
+) {
+    let mut data = Vec::new();
+    if gfx_select!(adapter_id => global.adapter_get_info(adapter_id)).is_ok() {
+        // Conservative defaults matching what `device_create_swap_chain`'s
+        // plain-texture pool can actually back today.
+        let capabilities = SurfaceCapabilities {
+            formats: vec![wgt::TextureFormat::Bgra8Unorm, wgt::TextureFormat::Rgba8Unorm],
+            present_modes: vec![wgt::PresentMode::Fifo],
+            alpha_modes: vec![wgt::CompositeAlphaMode::Opaque],
+            usages: (wgt::TextureUsages::RENDER_ATTACHMENT | wgt::TextureUsages::COPY_SRC).bits(),
+        };
+        bincode::serialize_into(&mut data, &capabilities).unwrap();
+    } else {
+        bincode::serialize_into(&mut data, &0u64).unwrap();
+    }
+    *byte_buf = ByteBuf::from_vec(data);
+}
+
 static TRACE_IDX: AtomicU32 = AtomicU32::new(0);
 
 #[no_mangle]
@@ -228,6 +430,17 @@ pub extern "C" fn wgpu_server_device_create_buffer(
             return;
         }
     };
+    if size > MAX_BUFFER_SIZE {
+        error_buf.init_str_typed(
+            ErrorBufferType::OutOfMemory,
+            &format!(
+                "Requested buffer size {} is larger than the maximum allowed size {}",
+                size, MAX_BUFFER_SIZE
+            ),
+        );
+        gfx_select!(self_id => global.create_buffer_error(buffer_id, label));
+        return;
+    }
     let desc = wgc::resource::BufferDescriptor {
         label,
         size,
@@ -251,7 +464,21 @@ pub unsafe extern "C" fn wgpu_server_buffer_map(
     size: wgt::BufferAddress,
     map_mode: wgc::device::HostMap,
     callback: wgc::resource::BufferMapCallbackC,
+    mut error_buf: ErrorBuffer,
 ) {
+    let end = match start.checked_add(size) {
+        Some(end) if end <= MAX_BUFFER_SIZE => end,
+        _ => {
+            error_buf.init_str_typed(
+                ErrorBufferType::OutOfMemory,
+                &format!(
+                    "Requested mapped range {}..{} is larger than the maximum allowed size {}",
+                    start, start.saturating_add(size), MAX_BUFFER_SIZE
+                ),
+            );
+            return;
+        }
+    };
     let callback = wgc::resource::BufferMapCallback::from_c(callback);
     let operation = wgc::resource::BufferMapOperation {
         host: map_mode,
@@ -259,7 +486,7 @@ pub unsafe extern "C" fn wgpu_server_buffer_map(
     };
     gfx_select!(buffer_id => global.buffer_map_async(
         buffer_id,
-        start .. start + size,
+        start .. end,
         operation
     ))
     .unwrap();
@@ -275,7 +502,21 @@ pub unsafe extern "C" fn wgpu_server_buffer_get_mapped_range(
     buffer_id: id::BufferId,
     start: wgt::BufferAddress,
     size: wgt::BufferAddress,
+    mut error_buf: ErrorBuffer,
 ) -> *mut u8 {
+    match start.checked_add(size) {
+        Some(end) if end <= MAX_BUFFER_SIZE => {}
+        _ => {
+            error_buf.init_str_typed(
+                ErrorBufferType::OutOfMemory,
+                &format!(
+                    "Requested mapped range {}..{} is larger than the maximum allowed size {}",
+                    start, start.saturating_add(size), MAX_BUFFER_SIZE
+                ),
+            );
+            return ptr::null_mut();
+        }
+    }
     gfx_select!(buffer_id => global.buffer_get_mapped_range(
         buffer_id,
         start,
@@ -304,6 +545,21 @@ impl Global {
     ) {
         match action {
             DeviceAction::CreateTexture(id, desc) => {
+                let size = &desc.size;
+                if size.width > MAX_TEXTURE_EXTENT
+                    || size.height > MAX_TEXTURE_EXTENT
+                    || size.depth_or_array_layers > MAX_TEXTURE_EXTENT
+                {
+                    error_buf.init_str_typed(
+                        ErrorBufferType::OutOfMemory,
+                        &format!(
+                            "Requested texture extent {}x{}x{} exceeds the maximum allowed extent {}",
+                            size.width, size.height, size.depth_or_array_layers, MAX_TEXTURE_EXTENT
+                        ),
+                    );
+                    self.create_texture_error(id, desc.label);
+                    return;
+                }
                 let (_, error) = self.device_create_texture::<A>(self_id, &desc, id);
                 if let Some(err) = error {
                     error_buf.init(err);
@@ -341,6 +597,9 @@ impl Global {
                 }
             }
             DeviceAction::CreateComputePipeline(id, desc, implicit) => {
+                if !validate_stage_constants(&desc.stage.constants, &mut error_buf) {
+                    return;
+                }
                 let implicit_ids = implicit
                     .as_ref()
                     .map(|imp| wgc::device::ImplicitPipelineIds {
@@ -354,6 +613,14 @@ impl Global {
                 }
             }
             DeviceAction::CreateRenderPipeline(id, desc, implicit) => {
+                if !validate_stage_constants(&desc.vertex.stage.constants, &mut error_buf)
+                    || !desc
+                        .fragment
+                        .as_ref()
+                        .map_or(true, |f| validate_stage_constants(&f.stage.constants, &mut error_buf))
+                {
+                    return;
+                }
                 let implicit_ids = implicit
                     .as_ref()
                     .map(|imp| wgc::device::ImplicitPipelineIds {
@@ -648,6 +915,26 @@ pub unsafe extern "C" fn wgpu_server_queue_write_action(
 ) {
     let action: QueueWriteAction = bincode::deserialize(byte_buf.as_slice()).unwrap();
     let data = slice::from_raw_parts(data, data_length);
+
+    let within_limit = match action {
+        QueueWriteAction::Buffer { offset, .. } => match offset.checked_add(data.len() as wgt::BufferAddress) {
+            Some(end) => end <= MAX_BUFFER_SIZE,
+            None => false,
+        },
+        QueueWriteAction::Texture { .. } => (data.len() as wgt::BufferAddress) <= MAX_BUFFER_SIZE,
+    };
+    if !within_limit {
+        error_buf.init_str_typed(
+            ErrorBufferType::OutOfMemory,
+            &format!(
+                "Requested write of {} bytes is larger than the maximum allowed size {}",
+                data.len(),
+                MAX_BUFFER_SIZE
+            ),
+        );
+        return;
+    }
+
     let result = match action {
         QueueWriteAction::Buffer { dst, offset } => {
             gfx_select!(self_id => global.queue_write_buffer(self_id, dst, offset, data))
@@ -661,6 +948,309 @@ pub unsafe extern "C" fn wgpu_server_queue_write_action(
     }
 }
 
+/// An opaque identifier for a swap chain, chosen by the content process.
+///
+/// Unlike the resource ids above, this isn't allocated through
+/// `IdentityRecyclerFactory`; the content process is free to pick any value
+/// that's unique among its live swap chains.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SwapChainId(pub u64);
+
+/// The parameters of a presentable surface, mirroring `GPUCanvasConfiguration`.
+#[repr(C)]
+pub struct SwapChainDescriptor {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgt::TextureFormat,
+    pub usage: u32,
+}
+
+impl Global {
+    /// Allocate `texture_ids.len()` textures matching `desc` and register
+    /// them as the presentable pool for `swap_chain_id`.
+    fn device_create_swap_chain<A: wgc::hub::HalApi>(
+        &self,
+        self_id: id::DeviceId,
+        swap_chain_id: SwapChainId,
+        desc: &SwapChainDescriptor,
+        texture_ids: &[id::TextureId],
+        mut error_buf: ErrorBuffer,
+    ) {
+        if texture_ids.is_empty() {
+            error_buf.init_str("Swap chain must be backed by at least one texture");
+            return;
+        }
+        let usage = match wgt::TextureUsages::from_bits(desc.usage) {
+            Some(usage) => usage,
+            None => {
+                error_buf.init_str(
+                    "GPUCanvasConfiguration's 'usage' includes invalid unimplemented bits \
+                     or unimplemented usages",
+                );
+                return;
+            }
+        };
+        if desc.width > MAX_TEXTURE_EXTENT || desc.height > MAX_TEXTURE_EXTENT {
+            error_buf.init_str_typed(
+                ErrorBufferType::OutOfMemory,
+                &format!(
+                    "Requested swap chain extent {}x{} exceeds the maximum allowed extent {}",
+                    desc.width, desc.height, MAX_TEXTURE_EXTENT
+                ),
+            );
+            return;
+        }
+        let texture_desc = wgc::resource::TextureDescriptor {
+            label: None,
+            size: wgt::Extent3d {
+                width: desc.width,
+                height: desc.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgt::TextureDimension::D2,
+            format: desc.format,
+            usage,
+        };
+
+        let mut textures = Vec::with_capacity(texture_ids.len());
+        for &texture_id in texture_ids {
+            let (_, error) = self.device_create_texture::<A>(self_id, &texture_desc, texture_id);
+            if let Some(err) = error {
+                error_buf.init(err);
+                for created_id in textures {
+                    gfx_select!(created_id => self.texture_drop(created_id, false));
+                }
+                return;
+            }
+            textures.push(texture_id);
+        }
+
+        self.swap_chains.lock().unwrap().insert(
+            swap_chain_id,
+            SwapChainData { textures, cursor: 0 },
+        );
+    }
+}
+
+/// Create the pool of textures a swap chain presents from.
+///
+/// # Safety
+///
+/// This function is unsafe as there is no guarantee that the given pointer is
+/// valid for `texture_id_length` elements.
+#[no_mangle]
+pub unsafe extern "C" fn wgpu_server_device_create_swap_chain(
+    global: &Global,
+    self_id: id::DeviceId,
+    swap_chain_id: SwapChainId,
+    desc: &SwapChainDescriptor,
+    texture_ids: *const id::TextureId,
+    texture_id_length: usize,
+    error_buf: ErrorBuffer,
+) {
+    let texture_ids = slice::from_raw_parts(texture_ids, texture_id_length);
+    gfx_select!(self_id => global.device_create_swap_chain(self_id, swap_chain_id, desc, texture_ids, error_buf));
+}
+
+/// Return the index, into the `texture_ids` passed to
+/// `wgpu_server_device_create_swap_chain`, of the texture the content process
+/// should render into next. Returns -1 if `swap_chain_id` names no live swap
+/// chain.
+///
+/// This is an `i32`, not the pool-sized `usize` `cursor` is stored as,
+/// because it's a cross-FFI return value with -1 reserved as a sentinel;
+/// an `i8` would truncate (and could go negative, colliding with that
+/// sentinel) for any pool past 127 textures.
+#[no_mangle]
+pub extern "C" fn wgpu_server_swap_chain_get_current_texture_id(
+    global: &Global,
+    swap_chain_id: SwapChainId,
+    mut error_buf: ErrorBuffer,
+) -> i32 {
+    match global.swap_chains.lock().unwrap().get(&swap_chain_id) {
+        Some(chain) => chain.cursor as i32,
+        None => {
+            error_buf.init_str("Swap chain has not been created or has already been destroyed");
+            -1
+        }
+    }
+}
+
+/// Submit `command_buffer_ids`, which must render into the swap chain's
+/// currently-acquired texture, and advance to the next texture in the pool.
+///
+/// Copying the presented texture into the shareable image the B2G
+/// compositor reads from happens on the other side of the IPC boundary in
+/// `WebGPUParent::SwapChainPresent`; this function only handles the
+/// wgpu-core side of submission and advancing the swap chain's cursor.
+///
+/// # Safety
+///
+/// This function is unsafe as there is no guarantee that the given pointer is
+/// valid for `command_buffer_id_length` elements.
+#[no_mangle]
+pub unsafe extern "C" fn wgpu_server_swap_chain_present(
+    global: &Global,
+    swap_chain_id: SwapChainId,
+    queue_id: id::QueueId,
+    command_buffer_ids: *const id::CommandBufferId,
+    command_buffer_id_length: usize,
+    mut error_buf: ErrorBuffer,
+) {
+    let has_chain = global.swap_chains.lock().unwrap().contains_key(&swap_chain_id);
+    if !has_chain {
+        error_buf.init_str("Swap chain has not been created or has already been destroyed");
+        return;
+    }
+
+    let command_buffers = slice::from_raw_parts(command_buffer_ids, command_buffer_id_length);
+    let result = gfx_select!(queue_id => global.queue_submit(queue_id, command_buffers));
+    if let Err(err) = result {
+        error_buf.init(err);
+        return;
+    }
+
+    if let Some(chain) = global.swap_chains.lock().unwrap().get_mut(&swap_chain_id) {
+        if !chain.textures.is_empty() {
+            chain.cursor = (chain.cursor + 1) % chain.textures.len();
+        }
+    }
+}
+
+/// Drop a swap chain and all the textures in its pool.
+#[no_mangle]
+pub extern "C" fn wgpu_server_swap_chain_destroy(global: &Global, swap_chain_id: SwapChainId) {
+    destroy_swap_chain(global, swap_chain_id);
+}
+
+fn destroy_swap_chain(global: &Global, swap_chain_id: SwapChainId) {
+    let chain = global.swap_chains.lock().unwrap().remove(&swap_chain_id);
+    if let Some(chain) = chain {
+        for texture_id in chain.textures {
+            gfx_select!(texture_id => global.texture_drop(texture_id, false));
+        }
+    }
+}
+
+/// Encode the freeing of a swap chain into a byte buf, for callers that
+/// batch it through the deferred `DropAction` path like the other
+/// `wgpu_server_*_free` functions instead of destroying it immediately.
+#[no_mangle]
+pub extern "C" fn wgpu_server_swap_chain_free(id: SwapChainId, drop_byte_buf: &mut ByteBuf) {
+    *drop_byte_buf = DropAction::SwapChain(id).to_byte_buf();
+}
+
+/// One entry in a recorded action trace: a resource id paired with the
+/// action that was dispatched against it, in original-capture order.
+///
+/// This reuses the same action enums (and the same `bincode` framing
+/// already used at the IPC boundary) as the replay instruction set, so a
+/// trace recorder only has to persist the bytes each `wgpu_server_*_action`
+/// entry point already receives. `QueueWrite`'s payload is embedded inline
+/// rather than referenced by filename: this tree doesn't carry
+/// wgpu-core's own `WGPU_TRACE` directory format (`trace.ron` plus numbered
+/// data files), so a trace file here is a self-contained, IPC-action-only
+/// substitute good enough to replay a captured crash headlessly.
+#[derive(serde::Serialize, serde::Deserialize)]
+enum ReplayAction {
+    Device(id::DeviceId, DeviceAction),
+    Texture(id::TextureId, TextureAction),
+    CommandEncoder(id::CommandEncoderId, CommandEncoderAction),
+    QueueWrite(id::QueueId, QueueWriteAction, Vec<u8>),
+}
+
+/// Replay a recorded action trace against `global`, stopping at the first
+/// error.
+///
+/// `path` must name a file holding a sequence of `bincode`-serialized
+/// `ReplayAction` entries, each prefixed by its encoded length as a
+/// little-endian `u32`.
+///
+/// Ids recorded in the trace are replayed as-is rather than being remapped
+/// through a fresh `IdentityRecyclerFactory` allocation: doing that needs a
+/// hub API for minting ids outside of the normal FFI entry points, which
+/// this tree doesn't have wired up. Replaying a trace against an
+/// otherwise-empty server -- the common case for reproducing a headless
+/// crash -- works fine without it.
+///
+/// # Safety
+///
+/// This function is unsafe because `path` must be a valid, null-terminated
+/// C string.
+#[no_mangle]
+pub unsafe extern "C" fn wgpu_server_replay_open(
+    global: &Global,
+    path: RawString,
+    mut error_buf: ErrorBuffer,
+) {
+    let path = std::ffi::CStr::from_ptr(path).to_string_lossy().into_owned();
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error_buf.init_str(&format!("Failed to read trace {:?}: {}", path, e));
+            return;
+        }
+    };
+
+    let mut offset = 0;
+    while offset < bytes.len() {
+        if offset + 4 > bytes.len() {
+            error_buf.init_str("Trace is truncated: incomplete entry length prefix");
+            return;
+        }
+        let len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+        if offset + len > bytes.len() {
+            error_buf.init_str("Trace is truncated: incomplete entry payload");
+            return;
+        }
+        let entry: ReplayAction = match bincode::deserialize(&bytes[offset..offset + len]) {
+            Ok(entry) => entry,
+            Err(e) => {
+                error_buf.init_str(&format!("Failed to decode trace entry at offset {}: {}", offset, e));
+                return;
+            }
+        };
+        offset += len;
+
+        match entry {
+            ReplayAction::Device(self_id, action) => {
+                gfx_select!(self_id => global.device_action(self_id, action, error_buf));
+            }
+            ReplayAction::Texture(self_id, action) => {
+                gfx_select!(self_id => global.texture_action(self_id, action, error_buf));
+            }
+            ReplayAction::CommandEncoder(self_id, action) => {
+                gfx_select!(self_id => global.command_encoder_action(self_id, action, error_buf));
+            }
+            ReplayAction::QueueWrite(self_id, action, data) => {
+                let result = match action {
+                    QueueWriteAction::Buffer { dst, offset } => {
+                        gfx_select!(self_id => global.queue_write_buffer(self_id, dst, offset, &data))
+                    }
+                    QueueWriteAction::Texture { dst, layout, size } => {
+                        gfx_select!(self_id => global.queue_write_texture(self_id, &dst, &data, &layout, &size))
+                    }
+                };
+                if let Err(err) = result {
+                    error_buf.init(err);
+                }
+            }
+        }
+
+        // `ErrorBuffer`'s contract (see its doc comment) is that the caller
+        // knows an error occurred once there's any text before the first
+        // null byte; that's exactly what we need to stop at the first
+        // failure and leave it in `error_buf` for the caller to report.
+        if *error_buf.string != 0 {
+            return;
+        }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn wgpu_server_bind_group_layout_drop(
     global: &Global,
@@ -707,11 +1297,237 @@ pub extern "C" fn wgpu_server_texture_view_drop(global: &Global, self_id: id::Te
     gfx_select!(self_id => global.texture_view_drop(self_id, false)).unwrap();
 }
 
+/// Windows D3D12 shared-resource texture import/export, for zero-copy
+/// interop with the OS compositor and other processes.
+///
+/// Both directions go through the device's/texture's hal representation via
+/// `Global::device_as_hal`/`Global::texture_as_hal`, reaching the `dx12`
+/// backend's `ID3D12Device`/`ID3D12Resource` COM objects directly; nothing
+/// here is specific to this tree beyond `gfx_select!`'s usual per-backend
+/// dispatch. An imported texture is an ordinary `id::TextureId` allocated
+/// the same way every other texture is (via `Global::create_texture_from_hal`),
+/// so it already participates in the normal `DropAction::Texture` lifecycle
+/// via `wgpu_server_texture_free` without further changes here.
+#[cfg(target_os = "windows")]
+mod d3d12_shared_handle {
+    use super::*;
+    use std::os::raw::c_void;
+    use windows::core::Interface;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::Graphics::Direct3D12::{ID3D12Device, ID3D12Resource};
+
+    /// Create a texture backed by an existing D3D12 shared resource.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid `HANDLE` returned by a prior
+    /// `CreateSharedHandle`/`DuplicateHandle` call on a D3D12 resource.
+    /// Ownership of `handle` transfers to this call: it is closed before
+    /// returning, whether or not the import succeeds.
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_server_device_create_texture_from_shared_handle(
+        global: &Global,
+        device_id: id::DeviceId,
+        desc: &wgc::resource::TextureDescriptor,
+        handle: *mut c_void,
+        assign_id: id::TextureId,
+        mut error_buf: ErrorBuffer,
+    ) {
+        if handle.is_null() {
+            error_buf.init_str_typed(ErrorBufferType::Validation, "Shared handle is null");
+            return;
+        }
+        let handle = HANDLE(handle as isize);
+
+        // Opening the handle only needs the `ID3D12Device` it was shared
+        // against, so do that before taking ownership of (and closing) the
+        // handle itself.
+        let opened: Result<ID3D12Resource, String> = gfx_select!(device_id => global
+            .device_as_hal::<wgc::hal::api::Dx12, _, _>(device_id, |hal_device| {
+                let hal_device = hal_device
+                    .ok_or_else(|| "device is not backed by the D3D12 hal backend".to_string())?;
+                let d3d12_device: &ID3D12Device = hal_device.raw_device();
+                d3d12_device
+                    .OpenSharedHandle(handle)
+                    .map_err(|e| format!("ID3D12Device::OpenSharedHandle failed: {e}"))
+            }));
+
+        CloseHandle(handle);
+
+        let resource = match opened {
+            Ok(resource) => resource,
+            Err(message) => {
+                error_buf.init_str_typed(ErrorBufferType::Internal, &message);
+                return;
+            }
+        };
+
+        let hal_texture = wgc::hal::dx12::Device::texture_from_raw(
+            resource,
+            desc.format,
+            desc.dimension,
+            desc.size,
+            desc.mip_level_count,
+            desc.sample_count,
+        );
+        let (_, error) = global.create_texture_from_hal::<wgc::hal::api::Dx12>(
+            hal_texture,
+            device_id,
+            desc,
+            assign_id,
+        );
+        if let Some(err) = error {
+            error_buf.init(err);
+        }
+    }
+
+    /// Create a shared handle for an existing texture's underlying
+    /// resource, written to `*out_handle` on success.
+    ///
+    /// # Safety
+    ///
+    /// `out_handle` must be valid for writes.
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_server_texture_get_shared_handle(
+        global: &Global,
+        texture_id: id::TextureId,
+        out_handle: *mut *mut c_void,
+        mut error_buf: ErrorBuffer,
+    ) {
+        *out_handle = ptr::null_mut();
+
+        let created: Result<HANDLE, String> = gfx_select!(texture_id => global
+            .texture_as_hal::<wgc::hal::api::Dx12, _, _>(texture_id, |hal_texture| {
+                let hal_texture = hal_texture
+                    .ok_or_else(|| "texture is not backed by the D3D12 hal backend".to_string())?;
+                let resource: &ID3D12Resource = hal_texture.raw_resource();
+                let device: ID3D12Device = resource
+                    .GetDevice()
+                    .map_err(|e| format!("ID3D12Resource::GetDevice failed: {e}"))?;
+                device
+                    .CreateSharedHandle(resource, None, 0x10000000 /* GENERIC_ALL */, None)
+                    .map_err(|e| format!("ID3D12Device::CreateSharedHandle failed: {e}"))
+            }));
+
+        match created {
+            Ok(handle) => *out_handle = handle.0 as *mut c_void,
+            Err(message) => error_buf.init_str_typed(ErrorBufferType::Internal, &message),
+        }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn wgpu_server_sampler_drop(global: &Global, self_id: id::SamplerId) {
     gfx_select!(self_id => global.sampler_drop(self_id));
 }
 
+/// One WGSL `override` constant override: the identifier name paired with
+/// the numeric value to substitute for it.
+#[repr(C)]
+pub struct ConstantEntry {
+    pub key: RawString,
+    pub value: f64,
+}
+
+/// Validate a raw array of pipeline-overridable constants and pack the
+/// resulting `key -> value` map, bincode-serialized, into `map_byte_buf`.
+/// The first invalid entry is surfaced through `error_buf` as a `Validation`
+/// error and `map_byte_buf` is not written.
+///
+/// The content process calls this to build the `constants` map and attaches
+/// it to the `ProgrammableStageDescriptor` of the `ComputePipelineDescriptor`
+/// or `RenderPipelineDescriptor` it serializes into a `CreateComputePipeline`
+/// / `CreateRenderPipeline` action. `device_action` re-validates that map
+/// (see `validate_stage_constants` below) before handing the descriptor to
+/// `device_create_compute_pipeline`/`device_create_render_pipeline`, since
+/// the map may have been tampered with in transit and wgpu-core applies it
+/// as-is when building the pipeline's override constants.
+///
+/// # Safety
+///
+/// This function is unsafe as there is no guarantee that the given pointer
+/// is valid for `constants_length` elements, or that each entry's `key` is a
+/// valid, null-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn wgpu_server_pipeline_constants_to_map(
+    constants: *const ConstantEntry,
+    constants_length: usize,
+    mut error_buf: ErrorBuffer,
+    map_byte_buf: &mut ByteBuf,
+) -> bool {
+    let entries = slice::from_raw_parts(constants, constants_length);
+    let mut map = std::collections::HashMap::with_capacity(entries.len());
+    for entry in entries {
+        let key = match std::ffi::CStr::from_ptr(entry.key).to_str() {
+            Ok(key) if !key.is_empty() => key,
+            _ => {
+                error_buf.init_str_typed(
+                    ErrorBufferType::Validation,
+                    "Pipeline-overridable constant key is empty or not valid UTF-8",
+                );
+                return false;
+            }
+        };
+        if !entry.value.is_finite() {
+            error_buf.init_str_typed(
+                ErrorBufferType::Validation,
+                &format!(
+                    "Pipeline-overridable constant {:?} has a non-finite value",
+                    key
+                ),
+            );
+            return false;
+        }
+        if map.insert(key.to_string(), entry.value).is_some() {
+            error_buf.init_str_typed(
+                ErrorBufferType::Validation,
+                &format!(
+                    "Pipeline-overridable constant {:?} is specified more than once",
+                    key
+                ),
+            );
+            return false;
+        }
+    }
+    let mut data = Vec::new();
+    bincode::serialize_into(&mut data, &map).unwrap();
+    *map_byte_buf = ByteBuf::from_vec(data);
+    true
+}
+
+/// Re-validate a stage's pipeline-overridable constants map at pipeline
+/// creation, rejecting the same things `wgpu_server_pipeline_constants_to_map`
+/// rejects (empty keys, non-finite values). Called from `device_action`'s
+/// `CreateComputePipeline`/`CreateRenderPipeline` arms right before the
+/// descriptor is handed to `device_create_compute_pipeline`/
+/// `device_create_render_pipeline`, so a constants map that was tampered
+/// with (or built by a non-conforming client) can't reach wgpu-core.
+fn validate_stage_constants(
+    constants: &std::collections::HashMap<String, f64>,
+    error_buf: &mut ErrorBuffer,
+) -> bool {
+    for (key, value) in constants {
+        if key.is_empty() {
+            error_buf.init_str_typed(
+                ErrorBufferType::Validation,
+                "Pipeline-overridable constant key is empty",
+            );
+            return false;
+        }
+        if !value.is_finite() {
+            error_buf.init_str_typed(
+                ErrorBufferType::Validation,
+                &format!(
+                    "Pipeline-overridable constant {:?} has a non-finite value",
+                    key
+                ),
+            );
+            return false;
+        }
+    }
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn wgpu_server_compute_pipeline_get_bind_group_layout(
     global: &Global,
@@ -722,7 +1538,8 @@ pub extern "C" fn wgpu_server_compute_pipeline_get_bind_group_layout(
 ) {
     let (_, error) = gfx_select!(self_id => global.compute_pipeline_get_bind_group_layout(self_id, index, assign_id));
     if let Some(err) = error {
-        error_buf.init(err);
+        let classified = ErrorBuffer::classify(err);
+        error_buf.init_str_typed(classified.ty, &classified.message);
     }
 }
 
@@ -736,7 +1553,8 @@ pub extern "C" fn wgpu_server_render_pipeline_get_bind_group_layout(
 ) {
     let (_, error) = gfx_select!(self_id => global.render_pipeline_get_bind_group_layout(self_id, index, assign_id));
     if let Some(err) = error {
-        error_buf.init(err);
+        let classified = ErrorBuffer::classify(err);
+        error_buf.init_str_typed(classified.ty, &classified.message);
     }
 }
 
@@ -821,3 +1639,131 @@ pub extern "C" fn wgpu_server_texture_view_free(
 pub extern "C" fn wgpu_server_sampler_free(id: id::SamplerId, drop_byte_buf: &mut ByteBuf) {
     *drop_byte_buf = DropAction::Sampler(id).to_byte_buf();
 }
+
+/// Which kind of resource a `DropActionEntry` names.
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum DropActionKind {
+    Adapter = 0,
+    Device = 1,
+    ShaderModule = 2,
+    PipelineLayout = 3,
+    BindGroupLayout = 4,
+    BindGroup = 5,
+    CommandBuffer = 6,
+    RenderBundle = 7,
+    RenderPipeline = 8,
+    ComputePipeline = 9,
+    Buffer = 10,
+    Texture = 11,
+    TextureView = 12,
+    Sampler = 13,
+    SwapChain = 14,
+}
+
+/// One entry in a batched free request: which kind of resource `id` names,
+/// packed as the raw 64-bit value every `id::Id<T>` is backed by.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DropActionEntry {
+    pub kind: DropActionKind,
+    pub id: u64,
+}
+
+/// Reinterpret a raw 64-bit id packed by the content process back into its
+/// typed form, rejecting `0` up front.
+///
+/// Every `id::Id<T>` is backed by a `NonZeroU64`, so transmuting a raw `0`
+/// straight into one is instant undefined behavior; going through
+/// `NonZeroU64::new` turns that into an ordinary `None`. The caller is still
+/// responsible for `entry.kind` matching the type `T` this is unpacked as.
+fn unpack_id<T>(raw: u64) -> Option<id::Id<T>> {
+    let nz = std::num::NonZeroU64::new(raw)?;
+    Some(unsafe { std::mem::transmute(nz) })
+}
+
+/// Encode the freeing of a whole batch of resources into a single byte buf.
+///
+/// A page teardown that drops thousands of objects would otherwise make one
+/// `wgpu_server_*_free` call (and one IPC-sized allocation) per object; this
+/// collects them into a single `DropAction::Batch` payload instead.
+///
+/// # Safety
+///
+/// This function is unsafe as there is no guarantee that the given pointer is
+/// valid for `len` elements.
+#[no_mangle]
+pub unsafe extern "C" fn wgpu_server_free_batch(
+    ids: *const DropActionEntry,
+    len: usize,
+    drop_byte_buf: &mut ByteBuf,
+) {
+    let entries = slice::from_raw_parts(ids, len);
+    let actions = entries
+        .iter()
+        .filter_map(|entry| {
+            Some(match entry.kind {
+                DropActionKind::Adapter => DropAction::Adapter(unpack_id(entry.id)?),
+                DropActionKind::Device => DropAction::Device(unpack_id(entry.id)?),
+                DropActionKind::ShaderModule => DropAction::ShaderModule(unpack_id(entry.id)?),
+                DropActionKind::PipelineLayout => {
+                    DropAction::PipelineLayout(unpack_id(entry.id)?)
+                }
+                DropActionKind::BindGroupLayout => {
+                    DropAction::BindGroupLayout(unpack_id(entry.id)?)
+                }
+                DropActionKind::BindGroup => DropAction::BindGroup(unpack_id(entry.id)?),
+                DropActionKind::CommandBuffer => DropAction::CommandBuffer(unpack_id(entry.id)?),
+                DropActionKind::RenderBundle => DropAction::RenderBundle(unpack_id(entry.id)?),
+                DropActionKind::RenderPipeline => {
+                    DropAction::RenderPipeline(unpack_id(entry.id)?)
+                }
+                DropActionKind::ComputePipeline => {
+                    DropAction::ComputePipeline(unpack_id(entry.id)?)
+                }
+                DropActionKind::Buffer => DropAction::Buffer(unpack_id(entry.id)?),
+                DropActionKind::Texture => DropAction::Texture(unpack_id(entry.id)?),
+                DropActionKind::TextureView => DropAction::TextureView(unpack_id(entry.id)?),
+                DropActionKind::Sampler => DropAction::Sampler(unpack_id(entry.id)?),
+                DropActionKind::SwapChain => DropAction::SwapChain(SwapChainId(entry.id)),
+            })
+        })
+        .collect();
+    *drop_byte_buf = DropAction::Batch(actions).to_byte_buf();
+}
+
+/// Deserialize and dispatch a batch of drops encoded by
+/// `wgpu_server_free_batch` (or a single drop encoded by any of the
+/// `wgpu_server_*_free` functions above).
+#[no_mangle]
+pub extern "C" fn wgpu_server_process_drop_batch(global: &Global, byte_buf: &ByteBuf) {
+    let action: DropAction = bincode::deserialize(byte_buf.as_slice()).unwrap();
+    dispatch_drop_action(global, action);
+}
+
+fn dispatch_drop_action(global: &Global, action: DropAction) {
+    match action {
+        DropAction::Adapter(id) => gfx_select!(id => global.adapter_drop(id)),
+        DropAction::Device(id) => gfx_select!(id => global.device_drop(id)),
+        DropAction::ShaderModule(id) => gfx_select!(id => global.shader_module_drop(id)),
+        DropAction::PipelineLayout(id) => gfx_select!(id => global.pipeline_layout_drop(id)),
+        DropAction::BindGroupLayout(id) => gfx_select!(id => global.bind_group_layout_drop(id)),
+        DropAction::BindGroup(id) => gfx_select!(id => global.bind_group_drop(id)),
+        DropAction::CommandBuffer(id) => gfx_select!(id => global.command_buffer_drop(id)),
+        DropAction::RenderBundle(id) => gfx_select!(id => global.render_bundle_drop(id)),
+        DropAction::RenderPipeline(id) => gfx_select!(id => global.render_pipeline_drop(id)),
+        DropAction::ComputePipeline(id) => gfx_select!(id => global.compute_pipeline_drop(id)),
+        DropAction::Buffer(id) => gfx_select!(id => global.buffer_drop(id, false)),
+        DropAction::Texture(id) => gfx_select!(id => global.texture_drop(id, false)),
+        DropAction::TextureView(id) => {
+            gfx_select!(id => global.texture_view_drop(id, false)).unwrap()
+        }
+        DropAction::Sampler(id) => gfx_select!(id => global.sampler_drop(id)),
+        DropAction::SwapChain(id) => destroy_swap_chain(global, id),
+        DropAction::Batch(actions) => {
+            for action in actions {
+                dispatch_drop_action(global, action);
+            }
+        }
+    }
+}
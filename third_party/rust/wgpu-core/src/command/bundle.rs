@@ -62,6 +62,30 @@ called. It goes through the commands and issues them into the native command
 buffer. Thanks to isolation, it doesn't track any bind group invalidations or
 index format changes.
 
+A bundle may contain timestamp and pipeline-statistics queries. Since the same
+bundle can be replayed into any number of render passes, a query recorded in a
+bundle is not tied to the bundle's own lifetime: each execution writes into
+whichever pass is currently running the bundle, at the point in that pass's
+command stream where the bundle is executed.
+
+`finish` resolves every resource id in the command stream to the `Arc` already
+held by the relevant `Storage`, producing an [`ArcRenderCommand`] stream. This
+keeps the bind groups, pipelines, buffers, and query sets a bundle touches
+alive for as long as the bundle itself, and means `execute` never needs to
+look them back up in `Storage`.
+
+### Native secondary/indirect command buffers
+
+Eventually, `finish` should be able to record the resolved command stream
+directly into a hal-level reusable command buffer -- a Vulkan secondary
+command buffer, or a Metal indirect command buffer -- once, and have
+`execute` simply replay that compiled buffer instead of re-issuing every
+`ArcRenderCommand`. [`RenderBundle::native_backing`] is the flag for
+that: it is set once at `finish` time, gated on `supports_native_backing`, and
+`execute` branches on it. No hal backend exposes a command-recording
+method to compile into, so the flag is always `false` and `execute`
+always falls back to software replay of the `ArcRenderCommand` stream.
+
 [Gdcrbe]: crate::hub::Global::device_create_render_bundle_encoder
 [Grbef]: crate::hub::Global::render_bundle_encoder_finish
 [wrpeb]: crate::command::render_ffi::wgpu_render_pass_execute_bundles
@@ -77,8 +101,8 @@ use crate::{
     },
     conv,
     device::{
-        AttachmentData, Device, DeviceError, MissingDownlevelFlags, RenderPassContext,
-        SHADER_STAGE_COUNT,
+        AttachmentData, Device, DeviceError, MissingDownlevelFlags, MissingFeatures,
+        RenderPassContext, SHADER_STAGE_COUNT,
     },
     error::{ErrorFormatter, PrettyError},
     hub::{GlobalIdentityHandlerFactory, HalApi, Hub, Resource, Storage, Token},
@@ -91,11 +115,23 @@ use crate::{
     Label, LabelHelpers, LifeGuard, Stored,
 };
 use arrayvec::ArrayVec;
-use std::{borrow::Cow, mem, num::NonZeroU32, ops::Range};
+use std::{borrow::Cow, mem, num::NonZeroU32, ops::Range, sync::Arc};
 use thiserror::Error;
 
 use hal::CommandEncoder as _;
 
+/// Identify a resource in error messages by its caller-supplied label, or,
+/// failing that, by its type and id, so a bundle validation failure reads
+/// like `Buffer "terrain-vertices" is not valid to use with this render
+/// bundle` instead of pointing at a bare, opaque id.
+fn resource_ident(kind: &'static str, label: &str, id: impl std::fmt::Debug) -> String {
+    if label.is_empty() {
+        format!("{kind} {id:?}")
+    } else {
+        format!("{kind} {label:?}")
+    }
+}
+
 /// Describes a [`RenderBundleEncoder`].
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
@@ -245,6 +281,7 @@ impl RenderBundleEncoder {
             flat_dynamic_offsets: Vec::new(),
             used_bind_groups: 0,
             pipeline: None,
+            active_pipeline_query: None,
         };
         let mut commands = Vec::new();
         let mut pipeline_layout_id = None::<id::Valid<id::PipelineLayoutId>>;
@@ -253,6 +290,157 @@ impl RenderBundleEncoder {
 
         let base = self.base.as_ref();
         let mut next_dynamic_offset = 0;
+        let next_string_offset = std::cell::Cell::new(0usize);
+        let mut debug_group_depth = 0u32;
+
+        // Every command actually issued to a bundle, after dedup, in its
+        // still-id-based form -- i.e. exactly what `resolve` below is about
+        // to turn into `ArcRenderCommand`s. Kept around (behind the `trace`
+        // feature) so capture/replay tooling can serialize the bundle's
+        // fully-resolved, deduplicated command stream instead of only the
+        // raw, pre-dedup `RenderBundleEncoder::to_base_pass` output.
+        #[cfg(feature = "trace")]
+        let resolved_commands = std::cell::RefCell::new(Vec::new());
+
+        // Resolve a normalized `RenderCommand` to its `ArcRenderCommand` form by
+        // looking up the resources it references one last time, so that
+        // `RenderBundle::execute` can hold onto them directly instead of going
+        // back through `Storage` on every replay.
+        let resolve = |command: RenderCommand| -> ArcRenderCommand<A> {
+            #[cfg(feature = "trace")]
+            resolved_commands.borrow_mut().push(command);
+
+            match command {
+                RenderCommand::SetBindGroup {
+                    index,
+                    num_dynamic_offsets,
+                    bind_group_id,
+                } => ArcRenderCommand::SetBindGroup {
+                    index,
+                    num_dynamic_offsets,
+                    bind_group: bind_group_guard.get(bind_group_id).unwrap().clone(),
+                },
+                RenderCommand::SetPipeline(pipeline_id) => {
+                    ArcRenderCommand::SetPipeline(pipeline_guard.get(pipeline_id).unwrap().clone())
+                }
+                RenderCommand::SetIndexBuffer {
+                    buffer_id,
+                    index_format,
+                    offset,
+                    size,
+                } => ArcRenderCommand::SetIndexBuffer {
+                    buffer: buffer_guard.get(buffer_id).unwrap().clone(),
+                    index_format,
+                    offset,
+                    size,
+                },
+                RenderCommand::SetVertexBuffer {
+                    slot,
+                    buffer_id,
+                    offset,
+                    size,
+                } => ArcRenderCommand::SetVertexBuffer {
+                    slot,
+                    buffer: buffer_guard.get(buffer_id).unwrap().clone(),
+                    offset,
+                    size,
+                },
+                RenderCommand::SetPushConstant {
+                    stages,
+                    offset,
+                    size_bytes,
+                    values_offset,
+                } => ArcRenderCommand::SetPushConstant {
+                    stages,
+                    offset,
+                    size_bytes,
+                    values_offset,
+                },
+                RenderCommand::Draw {
+                    vertex_count,
+                    instance_count,
+                    first_vertex,
+                    first_instance,
+                } => ArcRenderCommand::Draw {
+                    vertex_count,
+                    instance_count,
+                    first_vertex,
+                    first_instance,
+                },
+                RenderCommand::DrawIndexed {
+                    index_count,
+                    instance_count,
+                    first_index,
+                    base_vertex,
+                    first_instance,
+                } => ArcRenderCommand::DrawIndexed {
+                    index_count,
+                    instance_count,
+                    first_index,
+                    base_vertex,
+                    first_instance,
+                },
+                RenderCommand::MultiDrawIndirect {
+                    buffer_id,
+                    offset,
+                    count,
+                    indexed,
+                } => ArcRenderCommand::MultiDrawIndirect {
+                    buffer: buffer_guard.get(buffer_id).unwrap().clone(),
+                    offset,
+                    count,
+                    indexed,
+                },
+                RenderCommand::WriteTimestamp {
+                    query_set_id,
+                    query_index,
+                } => ArcRenderCommand::WriteTimestamp {
+                    query_set: query_set_guard.get(query_set_id).unwrap().clone(),
+                    query_index,
+                },
+                RenderCommand::BeginPipelineStatisticsQuery {
+                    query_set_id,
+                    query_index,
+                } => ArcRenderCommand::BeginPipelineStatisticsQuery {
+                    query_set: query_set_guard.get(query_set_id).unwrap().clone(),
+                    query_index,
+                },
+                RenderCommand::EndPipelineStatisticsQuery => {
+                    ArcRenderCommand::EndPipelineStatisticsQuery
+                }
+                RenderCommand::MultiDrawIndirectCount {
+                    buffer_id,
+                    offset,
+                    count_buffer_id,
+                    count_buffer_offset,
+                    max_count,
+                    indexed,
+                } => ArcRenderCommand::MultiDrawIndirectCount {
+                    buffer: buffer_guard.get(buffer_id).unwrap().clone(),
+                    offset,
+                    count_buffer: buffer_guard.get(count_buffer_id).unwrap().clone(),
+                    count_buffer_offset,
+                    max_count,
+                    indexed,
+                },
+                RenderCommand::PushDebugGroup { color, len } => {
+                    let offset = next_string_offset.get();
+                    next_string_offset.set(offset + len);
+                    ArcRenderCommand::PushDebugGroup { color, offset, len }
+                }
+                RenderCommand::InsertDebugMarker { color, len } => {
+                    let offset = next_string_offset.get();
+                    next_string_offset.set(offset + len);
+                    ArcRenderCommand::InsertDebugMarker { color, offset, len }
+                }
+                RenderCommand::PopDebugGroup => ArcRenderCommand::PopDebugGroup,
+                RenderCommand::ExecuteBundle(_)
+                | RenderCommand::SetBlendConstant(_)
+                | RenderCommand::SetStencilReference(_)
+                | RenderCommand::SetViewport { .. }
+                | RenderCommand::SetScissor(_) => unreachable!("not supported by a render bundle"),
+            }
+        };
 
         for &command in base.commands {
             match command {
@@ -269,8 +457,11 @@ impl RenderBundleEncoder {
                         .add_single(&*bind_group_guard, bind_group_id)
                         .ok_or(RenderCommandError::InvalidBindGroup(bind_group_id))
                         .map_pass_err(scope)?;
-                    self.check_valid_to_use(bind_group.device_id.value)
-                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        bind_group.device_id.value,
+                        resource_ident("BindGroup", bind_group.label(), bind_group_id),
+                    )
+                    .map_pass_err(scope)?;
 
                     let max_bind_groups = device.limits.max_bind_groups;
                     if (index as u32) >= max_bind_groups {
@@ -289,11 +480,14 @@ impl RenderBundleEncoder {
                     let offsets = &base.dynamic_offsets[offsets_range.clone()];
 
                     if bind_group.dynamic_binding_info.len() != offsets.len() {
-                        return Err(RenderCommandError::InvalidDynamicOffsetCount {
-                            actual: offsets.len(),
-                            expected: bind_group.dynamic_binding_info.len(),
-                        })
-                        .map_pass_err(scope);
+                        return Err(RenderBundleError {
+                            scope,
+                            inner: RenderBundleErrorInner::InvalidDynamicOffsetCount {
+                                bind_group_label: bind_group.label().to_string(),
+                                actual: offsets.len(),
+                                expected: bind_group.dynamic_binding_info.len(),
+                            },
+                        });
                     }
 
                     // Check for misaligned offsets.
@@ -305,10 +499,15 @@ impl RenderBundleEncoder {
                         let (alignment, limit_name) =
                             buffer_binding_type_alignment(&device.limits, info.binding_type);
                         if offset % alignment as u64 != 0 {
-                            return Err(RenderCommandError::UnalignedBufferOffset(
-                                offset, limit_name, alignment,
-                            ))
-                            .map_pass_err(scope);
+                            return Err(RenderBundleError {
+                                scope,
+                                inner: RenderBundleErrorInner::UnalignedBufferOffset {
+                                    bind_group_label: bind_group.label().to_string(),
+                                    offset,
+                                    limit_name,
+                                    alignment,
+                                },
+                            });
                         }
                     }
 
@@ -336,19 +535,31 @@ impl RenderBundleEncoder {
                         .add_single(&*pipeline_guard, pipeline_id)
                         .ok_or(RenderCommandError::InvalidPipeline(pipeline_id))
                         .map_pass_err(scope)?;
-                    self.check_valid_to_use(pipeline.device_id.value)
-                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        pipeline.device_id.value,
+                        resource_ident("RenderPipeline", pipeline.label(), pipeline_id),
+                    )
+                    .map_pass_err(scope)?;
 
                     self.context
                         .check_compatible(&pipeline.pass_context)
-                        .map_err(RenderCommandError::IncompatiblePipelineTargets)
-                        .map_pass_err(scope)?;
+                        .map_err(|source| RenderBundleError {
+                            scope,
+                            inner: RenderBundleErrorInner::IncompatiblePipelineTargets {
+                                pipeline_label: pipeline.label().to_string(),
+                                source: Box::new(source),
+                            },
+                        })?;
 
                     if pipeline.flags.contains(PipelineFlags::WRITES_DEPTH_STENCIL)
                         && self.is_ds_read_only
                     {
-                        return Err(RenderCommandError::IncompatiblePipelineRods)
-                            .map_pass_err(scope);
+                        return Err(RenderBundleError {
+                            scope,
+                            inner: RenderBundleErrorInner::IncompatiblePipelineRods {
+                                pipeline_label: pipeline.label().to_string(),
+                            },
+                        });
                     }
 
                     let layout = &pipeline_layout_guard[pipeline.layout_id.value];
@@ -360,9 +571,9 @@ impl RenderBundleEncoder {
                         &layout.bind_group_layout_ids,
                         &layout.push_constant_ranges,
                     );
-                    commands.push(command);
+                    commands.push(resolve(command));
                     if let Some(iter) = state.flush_push_constants() {
-                        commands.extend(iter)
+                        commands.extend(iter.map(resolve))
                     }
                 }
                 RenderCommand::SetIndexBuffer {
@@ -377,8 +588,11 @@ impl RenderBundleEncoder {
                         .buffers
                         .merge_single(&*buffer_guard, buffer_id, hal::BufferUses::INDEX)
                         .map_pass_err(scope)?;
-                    self.check_valid_to_use(buffer.device_id.value)
-                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        buffer.device_id.value,
+                        resource_ident("Buffer", buffer.label(), buffer_id),
+                    )
+                    .map_pass_err(scope)?;
                     check_buffer_usage(buffer.usage, wgt::BufferUsages::INDEX)
                         .map_pass_err(scope)?;
 
@@ -406,8 +620,11 @@ impl RenderBundleEncoder {
                         .buffers
                         .merge_single(&*buffer_guard, buffer_id, hal::BufferUses::VERTEX)
                         .map_pass_err(scope)?;
-                    self.check_valid_to_use(buffer.device_id.value)
-                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        buffer.device_id.value,
+                        resource_ident("Buffer", buffer.label(), buffer_id),
+                    )
+                    .map_pass_err(scope)?;
                     check_buffer_usage(buffer.usage, wgt::BufferUsages::VERTEX)
                         .map_pass_err(scope)?;
 
@@ -438,9 +655,15 @@ impl RenderBundleEncoder {
 
                     pipeline_layout
                         .validate_push_constant_ranges(stages, offset, end_offset)
-                        .map_pass_err(scope)?;
+                        .map_err(|source| RenderBundleError {
+                            scope,
+                            inner: RenderBundleErrorInner::InvalidPushConstantRange {
+                                pipeline_layout_label: pipeline_layout.label().to_string(),
+                                source: Box::new(source),
+                            },
+                        })?;
 
-                    commands.push(command);
+                    commands.push(resolve(command));
                 }
                 RenderCommand::Draw {
                     vertex_count,
@@ -472,9 +695,9 @@ impl RenderBundleEncoder {
                         })
                         .map_pass_err(scope);
                     }
-                    commands.extend(state.flush_vertices());
-                    commands.extend(state.flush_binds(base.dynamic_offsets));
-                    commands.push(command);
+                    commands.extend(state.flush_vertices().map(resolve));
+                    commands.extend(state.flush_binds(base.dynamic_offsets).map(resolve));
+                    commands.push(resolve(command));
                 }
                 RenderCommand::DrawIndexed {
                     index_count,
@@ -508,10 +731,10 @@ impl RenderBundleEncoder {
                         })
                         .map_pass_err(scope);
                     }
-                    commands.extend(state.index.flush());
-                    commands.extend(state.flush_vertices());
-                    commands.extend(state.flush_binds(base.dynamic_offsets));
-                    commands.push(command);
+                    commands.extend(state.index.flush().map(resolve));
+                    commands.extend(state.flush_vertices().map(resolve));
+                    commands.extend(state.flush_binds(base.dynamic_offsets).map(resolve));
+                    commands.push(resolve(command));
                 }
                 RenderCommand::MultiDrawIndirect {
                     buffer_id,
@@ -533,8 +756,11 @@ impl RenderBundleEncoder {
                         .buffers
                         .merge_single(&*buffer_guard, buffer_id, hal::BufferUses::INDIRECT)
                         .map_pass_err(scope)?;
-                    self.check_valid_to_use(buffer.device_id.value)
-                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        buffer.device_id.value,
+                        resource_ident("Buffer", buffer.label(), buffer_id),
+                    )
+                    .map_pass_err(scope)?;
                     check_buffer_usage(buffer.usage, wgt::BufferUsages::INDIRECT)
                         .map_pass_err(scope)?;
 
@@ -544,9 +770,9 @@ impl RenderBundleEncoder {
                         MemoryInitKind::NeedsInitializedMemory,
                     ));
 
-                    commands.extend(state.flush_vertices());
-                    commands.extend(state.flush_binds(base.dynamic_offsets));
-                    commands.push(command);
+                    commands.extend(state.flush_vertices().map(resolve));
+                    commands.extend(state.flush_binds(base.dynamic_offsets).map(resolve));
+                    commands.push(resolve(command));
                 }
                 RenderCommand::MultiDrawIndirect {
                     buffer_id,
@@ -568,30 +794,234 @@ impl RenderBundleEncoder {
                         .buffers
                         .merge_single(&*buffer_guard, buffer_id, hal::BufferUses::INDIRECT)
                         .map_pass_err(scope)?;
-                    self.check_valid_to_use(buffer.device_id.value)
+                    self.check_valid_to_use(
+                        buffer.device_id.value,
+                        resource_ident("Buffer", buffer.label(), buffer_id),
+                    )
+                    .map_pass_err(scope)?;
+                    check_buffer_usage(buffer.usage, wgt::BufferUsages::INDIRECT)
                         .map_pass_err(scope)?;
+
+                    buffer_memory_init_actions.extend(buffer.initialization_status.create_action(
+                        buffer_id,
+                        offset..(offset + mem::size_of::<wgt::DrawIndexedIndirectArgs>() as u64),
+                        MemoryInitKind::NeedsInitializedMemory,
+                    ));
+
+                    commands.extend(state.index.flush().map(resolve));
+                    commands.extend(state.flush_vertices().map(resolve));
+                    commands.extend(state.flush_binds(base.dynamic_offsets).map(resolve));
+                    commands.push(resolve(command));
+                }
+                RenderCommand::MultiDrawIndirect {
+                    buffer_id,
+                    offset,
+                    count: Some(count),
+                    indexed,
+                } => {
+                    let scope = PassErrorScope::Draw {
+                        indexed,
+                        indirect: true,
+                        pipeline: state.pipeline,
+                    };
+                    device
+                        .require_downlevel_flags(wgt::DownlevelFlags::INDIRECT_EXECUTION)
+                        .map_pass_err(scope)?;
+                    device
+                        .require_downlevel_flags(wgt::DownlevelFlags::MULTI_DRAW_INDIRECT)
+                        .map_pass_err(scope)?;
+
+                    let buffer: &resource::Buffer<A> = state
+                        .trackers
+                        .buffers
+                        .merge_single(&*buffer_guard, buffer_id, hal::BufferUses::INDIRECT)
+                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        buffer.device_id.value,
+                        resource_ident("Buffer", buffer.label(), buffer_id),
+                    )
+                    .map_pass_err(scope)?;
                     check_buffer_usage(buffer.usage, wgt::BufferUsages::INDIRECT)
                         .map_pass_err(scope)?;
 
+                    let stride = if indexed {
+                        mem::size_of::<wgt::DrawIndexedIndirectArgs>() as u64
+                    } else {
+                        mem::size_of::<wgt::DrawIndirectArgs>() as u64
+                    };
                     buffer_memory_init_actions.extend(buffer.initialization_status.create_action(
                         buffer_id,
-                        offset..(offset + mem::size_of::<wgt::DrawIndirectArgs>() as u64),
+                        offset..(offset + stride * count.get() as u64),
                         MemoryInitKind::NeedsInitializedMemory,
                     ));
 
-                    commands.extend(state.index.flush());
-                    commands.extend(state.flush_vertices());
-                    commands.extend(state.flush_binds(base.dynamic_offsets));
-                    commands.push(command);
+                    if indexed {
+                        commands.extend(state.index.flush().map(resolve));
+                    }
+                    commands.extend(state.flush_vertices().map(resolve));
+                    commands.extend(state.flush_binds(base.dynamic_offsets).map(resolve));
+                    commands.push(resolve(command));
+                }
+                RenderCommand::MultiDrawIndirectCount {
+                    buffer_id,
+                    offset,
+                    count_buffer_id,
+                    count_buffer_offset,
+                    max_count,
+                    indexed,
+                } => {
+                    let scope = PassErrorScope::Draw {
+                        indexed,
+                        indirect: true,
+                        pipeline: state.pipeline,
+                    };
+                    device
+                        .require_downlevel_flags(wgt::DownlevelFlags::INDIRECT_EXECUTION)
+                        .map_pass_err(scope)?;
+                    device
+                        .require_downlevel_flags(wgt::DownlevelFlags::MULTI_DRAW_INDIRECT_COUNT)
+                        .map_pass_err(scope)?;
+
+                    let buffer: &resource::Buffer<A> = state
+                        .trackers
+                        .buffers
+                        .merge_single(&*buffer_guard, buffer_id, hal::BufferUses::INDIRECT)
+                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        buffer.device_id.value,
+                        resource_ident("Buffer", buffer.label(), buffer_id),
+                    )
+                    .map_pass_err(scope)?;
+                    check_buffer_usage(buffer.usage, wgt::BufferUsages::INDIRECT)
+                        .map_pass_err(scope)?;
+
+                    let count_buffer: &resource::Buffer<A> = state
+                        .trackers
+                        .buffers
+                        .merge_single(&*buffer_guard, count_buffer_id, hal::BufferUses::INDIRECT)
+                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        count_buffer.device_id.value,
+                        resource_ident("Buffer", count_buffer.label(), count_buffer_id),
+                    )
+                    .map_pass_err(scope)?;
+                    check_buffer_usage(count_buffer.usage, wgt::BufferUsages::INDIRECT)
+                        .map_pass_err(scope)?;
+
+                    let stride = if indexed {
+                        mem::size_of::<wgt::DrawIndexedIndirectArgs>() as u64
+                    } else {
+                        mem::size_of::<wgt::DrawIndirectArgs>() as u64
+                    };
+                    buffer_memory_init_actions.extend(buffer.initialization_status.create_action(
+                        buffer_id,
+                        offset..(offset + stride * max_count as u64),
+                        MemoryInitKind::NeedsInitializedMemory,
+                    ));
+                    buffer_memory_init_actions.extend(
+                        count_buffer.initialization_status.create_action(
+                            count_buffer_id,
+                            count_buffer_offset..(count_buffer_offset + mem::size_of::<u32>() as u64),
+                            MemoryInitKind::NeedsInitializedMemory,
+                        ),
+                    );
+
+                    if indexed {
+                        commands.extend(state.index.flush().map(resolve));
+                    }
+                    commands.extend(state.flush_vertices().map(resolve));
+                    commands.extend(state.flush_binds(base.dynamic_offsets).map(resolve));
+                    commands.push(resolve(command));
+                }
+                RenderCommand::PushDebugGroup { .. } => {
+                    debug_group_depth += 1;
+                    commands.push(resolve(command));
+                }
+                RenderCommand::InsertDebugMarker { .. } => {
+                    commands.push(resolve(command));
+                }
+                RenderCommand::PopDebugGroup => {
+                    debug_group_depth = match debug_group_depth.checked_sub(1) {
+                        Some(depth) => depth,
+                        None => {
+                            return Err(RenderBundleError {
+                                scope: PassErrorScope::Bundle,
+                                inner: RenderBundleErrorInner::UnbalancedDebugGroupPop,
+                            })
+                        }
+                    };
+                    commands.push(resolve(command));
+                }
+                RenderCommand::WriteTimestamp {
+                    query_set_id,
+                    query_index,
+                } => {
+                    let scope = PassErrorScope::WriteTimestamp;
+                    device
+                        .require_features(wgt::Features::TIMESTAMP_QUERY_INSIDE_PASSES)
+                        .map_pass_err(scope)?;
+
+                    let query_set: &resource::QuerySet<A> = state
+                        .trackers
+                        .query_sets
+                        .add_single(&*query_set_guard, query_set_id)
+                        .ok_or(RenderCommandError::InvalidQuerySet(query_set_id))
+                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        query_set.device_id.value,
+                        resource_ident("QuerySet", query_set.label(), query_set_id),
+                    )
+                    .map_pass_err(scope)?;
+                    query_set
+                        .validate_query(query_set_id, query_index, None)
+                        .map_pass_err(scope)?;
+
+                    commands.push(resolve(command));
+                }
+                RenderCommand::BeginPipelineStatisticsQuery {
+                    query_set_id,
+                    query_index,
+                } => {
+                    let scope = PassErrorScope::BeginPipelineStatisticsQuery;
+                    device
+                        .require_features(wgt::Features::PIPELINE_STATISTICS_QUERY)
+                        .map_pass_err(scope)?;
+
+                    let query_set: &resource::QuerySet<A> = state
+                        .trackers
+                        .query_sets
+                        .add_single(&*query_set_guard, query_set_id)
+                        .ok_or(RenderCommandError::InvalidQuerySet(query_set_id))
+                        .map_pass_err(scope)?;
+                    self.check_valid_to_use(
+                        query_set.device_id.value,
+                        resource_ident("QuerySet", query_set.label(), query_set_id),
+                    )
+                    .map_pass_err(scope)?;
+                    query_set
+                        .validate_query(query_set_id, query_index, None)
+                        .map_pass_err(scope)?;
+
+                    if state.active_pipeline_query.replace((query_set_id, query_index)).is_some() {
+                        return Err(RenderCommandError::UnbalancedPipelineStatisticsQuery)
+                            .map_pass_err(scope);
+                    }
+
+                    commands.push(resolve(command));
+                }
+                RenderCommand::EndPipelineStatisticsQuery => {
+                    let scope = PassErrorScope::EndPipelineStatisticsQuery;
+                    device
+                        .require_features(wgt::Features::PIPELINE_STATISTICS_QUERY)
+                        .map_pass_err(scope)?;
+
+                    if state.active_pipeline_query.take().is_none() {
+                        return Err(RenderCommandError::UnbalancedPipelineStatisticsQuery)
+                            .map_pass_err(scope);
+                    }
+
+                    commands.push(resolve(command));
                 }
-                RenderCommand::MultiDrawIndirect { .. }
-                | RenderCommand::MultiDrawIndirectCount { .. } => unimplemented!(),
-                RenderCommand::PushDebugGroup { color: _, len: _ } => unimplemented!(),
-                RenderCommand::InsertDebugMarker { color: _, len: _ } => unimplemented!(),
-                RenderCommand::PopDebugGroup => unimplemented!(),
-                RenderCommand::WriteTimestamp { .. }
-                | RenderCommand::BeginPipelineStatisticsQuery { .. }
-                | RenderCommand::EndPipelineStatisticsQuery => unimplemented!(),
                 RenderCommand::ExecuteBundle(_)
                 | RenderCommand::SetBlendConstant(_)
                 | RenderCommand::SetStencilReference(_)
@@ -600,14 +1030,28 @@ impl RenderBundleEncoder {
             }
         }
 
+        if state.active_pipeline_query.is_some() {
+            return Err(RenderCommandError::UnbalancedPipelineStatisticsQuery)
+                .map_pass_err(PassErrorScope::Bundle);
+        }
+
+        if debug_group_depth != 0 {
+            return Err(RenderBundleError {
+                scope: PassErrorScope::Bundle,
+                inner: RenderBundleErrorInner::UnbalancedDebugGroupPush,
+            });
+        }
+
         Ok(RenderBundle {
             base: BasePass {
                 label: desc.label.as_ref().map(|cow| cow.to_string()),
                 commands,
                 dynamic_offsets: state.flat_dynamic_offsets,
-                string_data: Vec::new(),
+                string_data: base.string_data.to_vec(),
                 push_constant_data: Vec::new(),
             },
+            #[cfg(feature = "trace")]
+            resolved_commands: resolved_commands.into_inner(),
             is_ds_read_only: self.is_ds_read_only,
             device_id: Stored {
                 value: id::Valid(self.parent_id),
@@ -618,15 +1062,33 @@ impl RenderBundleEncoder {
             texture_memory_init_actions,
             context: self.context,
             life_guard: LifeGuard::new(desc.label.borrow_or_default()),
+            native_backing: if supports_native_backing(device) {
+                // Once a hal backend reports the capability, this is where
+                // `commands` would get compiled once into a backend-native
+                // reusable object via `begin_bundle`/`end_bundle` on the hal
+                // `CommandEncoder`, instead of being replayed by
+                // `RenderBundle::execute` on every use. No such hal method
+                // exists yet, so if `supports_native_backing` is ever
+                // flipped to `true` ahead of one landing, debug builds trip
+                // the assertion below and release builds stay `false`.
+                debug_assert!(
+                    false,
+                    "native render bundle backing capability reported but not implemented"
+                );
+                false
+            } else {
+                false
+            },
         })
     }
 
     fn check_valid_to_use(
         &self,
         device_id: id::Valid<id::DeviceId>,
+        resource_ident: String,
     ) -> Result<(), RenderBundleErrorInner> {
         if device_id.0 != self.parent_id {
-            return Err(RenderBundleErrorInner::NotValidToUse);
+            return Err(RenderBundleErrorInner::NotValidToUse { resource_ident });
         }
 
         Ok(())
@@ -660,32 +1122,136 @@ pub enum CreateRenderBundleError {
 /// Error type returned from `RenderBundleEncoder::new` if the sample count is invalid.
 #[derive(Clone, Debug, Error)]
 pub enum ExecutionError {
-    #[error("buffer {0:?} is destroyed")]
-    DestroyedBuffer(id::BufferId),
+    #[error("buffer is destroyed")]
+    DestroyedBuffer,
     #[error("using {0} in a render bundle is not implemented")]
     Unimplemented(&'static str),
 }
 impl PrettyError for ExecutionError {
     fn fmt_pretty(&self, fmt: &mut ErrorFormatter) {
         fmt.error(self);
-        match *self {
-            Self::DestroyedBuffer(id) => {
-                fmt.buffer_label(&id);
-            }
-            Self::Unimplemented(_reason) => {}
-        };
     }
 }
 
 pub type RenderBundleDescriptor<'a> = wgt::RenderBundleDescriptor<Label<'a>>;
 
-//Note: here, `RenderBundle` is just wrapping a raw stream of render commands.
-// The plan is to back it by an actual Vulkan secondary buffer, D3D12 Bundle,
-// or Metal indirect command buffer.
+/// Like [`RenderCommand`], but with the ids it carries already resolved into
+/// the strong `Arc` handles held by the `Storage` guards at
+/// [`RenderBundleEncoder::finish`] time.
+///
+/// Holding these `Arc`s directly means `RenderBundle::execute` doesn't need
+/// to go back through `Storage` (and thus doesn't need the `Storage` guards
+/// at all for these resource kinds), and keeps the referenced resources
+/// alive for exactly as long as the bundle that needs them, independent of
+/// the `RenderBundleScope` trackers.
+#[derive(Debug)]
+pub(super) enum ArcRenderCommand<A: HalApi> {
+    SetBindGroup {
+        index: u8,
+        num_dynamic_offsets: u8,
+        bind_group: Arc<binding_model::BindGroup<A>>,
+    },
+    SetPipeline(Arc<pipeline::RenderPipeline<A>>),
+    SetIndexBuffer {
+        buffer: Arc<resource::Buffer<A>>,
+        index_format: wgt::IndexFormat,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    },
+    SetVertexBuffer {
+        slot: u32,
+        buffer: Arc<resource::Buffer<A>>,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    },
+    SetPushConstant {
+        stages: wgt::ShaderStages,
+        offset: u32,
+        size_bytes: u32,
+        values_offset: Option<u32>,
+    },
+    Draw {
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    },
+    DrawIndexed {
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        base_vertex: i32,
+        first_instance: u32,
+    },
+    MultiDrawIndirect {
+        buffer: Arc<resource::Buffer<A>>,
+        offset: wgt::BufferAddress,
+        count: Option<NonZeroU32>,
+        indexed: bool,
+    },
+    MultiDrawIndirectCount {
+        buffer: Arc<resource::Buffer<A>>,
+        offset: wgt::BufferAddress,
+        count_buffer: Arc<resource::Buffer<A>>,
+        count_buffer_offset: wgt::BufferAddress,
+        max_count: u32,
+        indexed: bool,
+    },
+    WriteTimestamp {
+        query_set: Arc<resource::QuerySet<A>>,
+        query_index: u32,
+    },
+    BeginPipelineStatisticsQuery {
+        query_set: Arc<resource::QuerySet<A>>,
+        query_index: u32,
+    },
+    EndPipelineStatisticsQuery,
+    PushDebugGroup {
+        color: u32,
+        offset: usize,
+        len: usize,
+    },
+    InsertDebugMarker {
+        color: u32,
+        offset: usize,
+        len: usize,
+    },
+    PopDebugGroup,
+}
+
+/// Whether `device`'s hal backend can back a render bundle with a native
+/// secondary/indirect command buffer instead of falling back to software
+/// replay of the `ArcRenderCommand` stream.
+///
+/// The intended shape of that path is a `begin_bundle`/`end_bundle` pair on
+/// the hal `CommandEncoder` trait, recording the resolved command stream
+/// once into a backend-native reusable object (a Vulkan secondary command
+/// buffer, a D3D12 bundle, or a Metal indirect command buffer) at
+/// [`RenderBundleEncoder::finish`] time, cached on the [`RenderBundle`], and
+/// replayed with a single `execute_bundle` call instead of re-issuing every
+/// `ArcRenderCommand`. No hal backend implements that trait method yet, so
+/// this always returns `false` and bundles always fall back to software
+/// replay; see [`RenderBundle::uses_native_backing`] for how callers can
+/// observe the result of this check for a given bundle.
+fn supports_native_backing<A: HalApi>(_device: &Device<A>) -> bool {
+    false
+}
+
+//Note: here, `RenderBundle` is just wrapping a stream of render commands,
+// resolved to the resources they reference.
 pub struct RenderBundle<A: HalApi> {
-    // Normalized command stream. It can be executed verbatim,
-    // without re-binding anything on the pipeline change.
-    base: BasePass<RenderCommand>,
+    // Normalized, resolved command stream. It can be executed verbatim,
+    // without re-binding anything on the pipeline change, and without
+    // looking bind groups, pipelines, buffers or query sets up in `Storage`
+    // again (pipeline layouts still are).
+    base: BasePass<ArcRenderCommand<A>>,
+    /// The same command stream as `base.commands`, still in its pre-`resolve`,
+    /// id-based `RenderCommand` form. Lets [`RenderBundle::to_replay_trace`]
+    /// serialize the bundle's fully-resolved, deduplicated command stream for
+    /// capture/replay tooling without needing to recover ids from the `Arc`s
+    /// in `base.commands`.
+    #[cfg(feature = "trace")]
+    resolved_commands: Vec<RenderCommand>,
     pub(super) is_ds_read_only: bool,
     pub(crate) device_id: Stored<id::DeviceId>,
     pub(crate) used: RenderBundleScope<A>,
@@ -693,12 +1259,53 @@ pub struct RenderBundle<A: HalApi> {
     pub(super) texture_memory_init_actions: Vec<TextureInitTrackerAction>,
     pub(super) context: RenderPassContext,
     pub(crate) life_guard: LifeGuard,
+    /// Whether this bundle is backed by a compiled native secondary/indirect
+    /// command buffer (see the module docs' "Native secondary/indirect
+    /// command buffers" section) rather than software replay. `true` only
+    /// when the backing device's hal backend reported
+    /// [`supports_native_backing`] at [`RenderBundleEncoder::finish`] time;
+    /// always `false` today, since no hal backend does. This is a plain flag
+    /// rather than a compiled `A::CommandBuffer`: no hal backend in this tree
+    /// exposes a `begin_bundle`/`end_bundle`-style recording method to
+    /// produce one with, or an `execute_bundle` method to replay one.
+    pub(super) native_backing: bool,
 }
 
 unsafe impl<A: HalApi> Send for RenderBundle<A> {}
 unsafe impl<A: HalApi> Sync for RenderBundle<A> {}
 
 impl<A: HalApi> RenderBundle<A> {
+    /// Whether this bundle is backed by a native secondary/indirect command
+    /// buffer rather than software replay of its `ArcRenderCommand` stream.
+    ///
+    /// This is decided once, at `finish` time, from the backing device's
+    /// hal capabilities, so it never changes over the bundle's lifetime.
+    /// Always `false` today; see [`supports_native_backing`].
+    pub fn uses_native_backing(&self) -> bool {
+        self.native_backing
+    }
+
+    /// Emit this bundle's fully-resolved, deduplicated command stream as a
+    /// serializable [`BasePass`], suitable for writing a trace to disk and
+    /// replaying it byte-for-byte on another device.
+    ///
+    /// Unlike [`RenderBundleEncoder::to_base_pass`], which captures the raw
+    /// commands as originally recorded, this reflects the output of the
+    /// `State` flush pipeline (`flush_binds`, `flush_vertices`,
+    /// `flush_push_constants`) that `finish` already ran -- the same
+    /// commands `execute` replays, just with their resources still
+    /// expressed as ids instead of resolved `Arc`s.
+    #[cfg(feature = "trace")]
+    pub fn to_replay_trace(&self) -> BasePass<RenderCommand> {
+        BasePass {
+            label: self.base.label.clone(),
+            commands: self.resolved_commands.clone(),
+            dynamic_offsets: self.base.dynamic_offsets.clone(),
+            string_data: self.base.string_data.clone(),
+            push_constant_data: self.base.push_constant_data.clone(),
+        }
+    }
+
     /// Actually encode the contents into a native command buffer.
     ///
     /// This is partially duplicating the logic of `command_encoder_run_render_pass`.
@@ -715,77 +1322,83 @@ impl<A: HalApi> RenderBundle<A> {
             crate::binding_model::PipelineLayout<A>,
             id::PipelineLayoutId,
         >,
-        bind_group_guard: &Storage<crate::binding_model::BindGroup<A>, id::BindGroupId>,
-        pipeline_guard: &Storage<crate::pipeline::RenderPipeline<A>, id::RenderPipelineId>,
-        buffer_guard: &Storage<crate::resource::Buffer<A>, id::BufferId>,
     ) -> Result<(), ExecutionError> {
+        if self.native_backing {
+            // `native_backing` is never `true` today (see
+            // `supports_native_backing`): no hal backend in this tree has a
+            // `begin_bundle`/`end_bundle`-style method for `finish` to have
+            // compiled a native object with, nor an `execute_bundle` method
+            // to replay one through here. This is where that replay would
+            // go once one exists.
+            debug_assert!(
+                false,
+                "native render bundle backing capability reported but not implemented"
+            );
+            return Ok(());
+        }
+
         let mut offsets = self.base.dynamic_offsets.as_slice();
         let mut pipeline_layout_id = None::<id::Valid<id::PipelineLayoutId>>;
+        let mut active_statistics_query = None::<(&Arc<resource::QuerySet<A>>, u32)>;
         if let Some(ref label) = self.base.label {
             raw.begin_debug_marker(label);
         }
 
         for command in self.base.commands.iter() {
-            match *command {
-                RenderCommand::SetBindGroup {
+            match command {
+                ArcRenderCommand::SetBindGroup {
                     index,
                     num_dynamic_offsets,
-                    bind_group_id,
+                    bind_group,
                 } => {
-                    let bind_group = bind_group_guard.get(bind_group_id).unwrap();
                     raw.set_bind_group(
                         &pipeline_layout_guard[pipeline_layout_id.unwrap()].raw,
-                        index as u32,
+                        *index as u32,
                         &bind_group.raw,
-                        &offsets[..num_dynamic_offsets as usize],
+                        &offsets[..*num_dynamic_offsets as usize],
                     );
-                    offsets = &offsets[num_dynamic_offsets as usize..];
+                    offsets = &offsets[*num_dynamic_offsets as usize..];
                 }
-                RenderCommand::SetPipeline(pipeline_id) => {
-                    let pipeline = pipeline_guard.get(pipeline_id).unwrap();
+                ArcRenderCommand::SetPipeline(pipeline) => {
                     raw.set_render_pipeline(&pipeline.raw);
 
                     pipeline_layout_id = Some(pipeline.layout_id.value);
                 }
-                RenderCommand::SetIndexBuffer {
-                    buffer_id,
+                ArcRenderCommand::SetIndexBuffer {
+                    buffer,
                     index_format,
                     offset,
                     size,
                 } => {
-                    let buffer = buffer_guard
-                        .get(buffer_id)
-                        .unwrap()
+                    let buffer = buffer
                         .raw
                         .as_ref()
-                        .ok_or(ExecutionError::DestroyedBuffer(buffer_id))?;
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
                     let bb = hal::BufferBinding {
                         buffer,
-                        offset,
-                        size,
+                        offset: *offset,
+                        size: *size,
                     };
-                    raw.set_index_buffer(bb, index_format);
+                    raw.set_index_buffer(bb, *index_format);
                 }
-                RenderCommand::SetVertexBuffer {
+                ArcRenderCommand::SetVertexBuffer {
                     slot,
-                    buffer_id,
+                    buffer,
                     offset,
                     size,
                 } => {
-                    let buffer = buffer_guard
-                        .get(buffer_id)
-                        .unwrap()
+                    let buffer = buffer
                         .raw
                         .as_ref()
-                        .ok_or(ExecutionError::DestroyedBuffer(buffer_id))?;
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
                     let bb = hal::BufferBinding {
                         buffer,
-                        offset,
-                        size,
+                        offset: *offset,
+                        size: *size,
                     };
-                    raw.set_vertex_buffer(slot, bb);
+                    raw.set_vertex_buffer(*slot, bb);
                 }
-                RenderCommand::SetPushConstant {
+                ArcRenderCommand::SetPushConstant {
                     stages,
                     offset,
                     size_bytes,
@@ -794,21 +1407,21 @@ impl<A: HalApi> RenderBundle<A> {
                     let pipeline_layout_id = pipeline_layout_id.unwrap();
                     let pipeline_layout = &pipeline_layout_guard[pipeline_layout_id];
 
-                    if let Some(values_offset) = values_offset {
+                    if let Some(values_offset) = *values_offset {
                         let values_end_offset =
                             (values_offset + size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize;
                         let data_slice = &self.base.push_constant_data
                             [(values_offset as usize)..values_end_offset];
 
-                        raw.set_push_constants(&pipeline_layout.raw, stages, offset, data_slice)
+                        raw.set_push_constants(&pipeline_layout.raw, *stages, *offset, data_slice)
                     } else {
                         super::push_constant_clear(
-                            offset,
-                            size_bytes,
+                            *offset,
+                            *size_bytes,
                             |clear_offset, clear_data| {
                                 raw.set_push_constants(
                                     &pipeline_layout.raw,
-                                    stages,
+                                    *stages,
                                     clear_offset,
                                     clear_data,
                                 );
@@ -816,15 +1429,15 @@ impl<A: HalApi> RenderBundle<A> {
                         );
                     }
                 }
-                RenderCommand::Draw {
+                ArcRenderCommand::Draw {
                     vertex_count,
                     instance_count,
                     first_vertex,
                     first_instance,
                 } => {
-                    raw.draw(first_vertex, vertex_count, first_instance, instance_count);
+                    raw.draw(*first_vertex, *vertex_count, *first_instance, *instance_count);
                 }
-                RenderCommand::DrawIndexed {
+                ArcRenderCommand::DrawIndexed {
                     index_count,
                     instance_count,
                     first_index,
@@ -832,60 +1445,151 @@ impl<A: HalApi> RenderBundle<A> {
                     first_instance,
                 } => {
                     raw.draw_indexed(
-                        first_index,
-                        index_count,
-                        base_vertex,
-                        first_instance,
-                        instance_count,
+                        *first_index,
+                        *index_count,
+                        *base_vertex,
+                        *first_instance,
+                        *instance_count,
                     );
                 }
-                RenderCommand::MultiDrawIndirect {
-                    buffer_id,
+                ArcRenderCommand::MultiDrawIndirect {
+                    buffer,
                     offset,
                     count: None,
                     indexed: false,
                 } => {
-                    let buffer = buffer_guard
-                        .get(buffer_id)
-                        .unwrap()
+                    let buffer = buffer
                         .raw
                         .as_ref()
-                        .ok_or(ExecutionError::DestroyedBuffer(buffer_id))?;
-                    raw.draw_indirect(buffer, offset, 1);
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
+                    raw.draw_indirect(buffer, *offset, 1);
                 }
-                RenderCommand::MultiDrawIndirect {
-                    buffer_id,
+                ArcRenderCommand::MultiDrawIndirect {
+                    buffer,
                     offset,
                     count: None,
                     indexed: true,
                 } => {
-                    let buffer = buffer_guard
-                        .get(buffer_id)
-                        .unwrap()
+                    let buffer = buffer
                         .raw
                         .as_ref()
-                        .ok_or(ExecutionError::DestroyedBuffer(buffer_id))?;
-                    raw.draw_indexed_indirect(buffer, offset, 1);
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
+                    raw.draw_indexed_indirect(buffer, *offset, 1);
                 }
-                RenderCommand::MultiDrawIndirect { .. }
-                | RenderCommand::MultiDrawIndirectCount { .. } => {
-                    return Err(ExecutionError::Unimplemented("multi-draw-indirect"))
+                ArcRenderCommand::MultiDrawIndirect {
+                    buffer,
+                    offset,
+                    count: Some(count),
+                    indexed: false,
+                } => {
+                    let buffer = buffer
+                        .raw
+                        .as_ref()
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
+                    raw.draw_indirect(buffer, *offset, count.get());
                 }
-                RenderCommand::PushDebugGroup { .. }
-                | RenderCommand::InsertDebugMarker { .. }
-                | RenderCommand::PopDebugGroup => {
-                    return Err(ExecutionError::Unimplemented("debug-markers"))
+                ArcRenderCommand::MultiDrawIndirect {
+                    buffer,
+                    offset,
+                    count: Some(count),
+                    indexed: true,
+                } => {
+                    let buffer = buffer
+                        .raw
+                        .as_ref()
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
+                    raw.draw_indexed_indirect(buffer, *offset, count.get());
                 }
-                RenderCommand::WriteTimestamp { .. }
-                | RenderCommand::BeginPipelineStatisticsQuery { .. }
-                | RenderCommand::EndPipelineStatisticsQuery => {
-                    return Err(ExecutionError::Unimplemented("queries"))
+                ArcRenderCommand::MultiDrawIndirectCount {
+                    buffer,
+                    offset,
+                    count_buffer,
+                    count_buffer_offset,
+                    max_count,
+                    indexed: false,
+                } => {
+                    let buffer = buffer
+                        .raw
+                        .as_ref()
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
+                    let count_buffer = count_buffer
+                        .raw
+                        .as_ref()
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
+                    raw.draw_indirect_count(
+                        buffer,
+                        *offset,
+                        count_buffer,
+                        *count_buffer_offset,
+                        *max_count,
+                    );
+                }
+                ArcRenderCommand::MultiDrawIndirectCount {
+                    buffer,
+                    offset,
+                    count_buffer,
+                    count_buffer_offset,
+                    max_count,
+                    indexed: true,
+                } => {
+                    let buffer = buffer
+                        .raw
+                        .as_ref()
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
+                    let count_buffer = count_buffer
+                        .raw
+                        .as_ref()
+                        .ok_or(ExecutionError::DestroyedBuffer)?;
+                    raw.draw_indexed_indirect_count(
+                        buffer,
+                        *offset,
+                        count_buffer,
+                        *count_buffer_offset,
+                        *max_count,
+                    );
+                }
+                ArcRenderCommand::WriteTimestamp {
+                    query_set,
+                    query_index,
+                } => {
+                    raw.write_timestamp(&query_set.raw, *query_index);
+                }
+                ArcRenderCommand::BeginPipelineStatisticsQuery {
+                    query_set,
+                    query_index,
+                } => {
+                    raw.begin_query(&query_set.raw, *query_index);
+                    active_statistics_query = Some((query_set, *query_index));
+                }
+                ArcRenderCommand::EndPipelineStatisticsQuery => {
+                    // `finish` already verified that every `EndPipelineStatisticsQuery`
+                    // is paired with a preceding `BeginPipelineStatisticsQuery`.
+                    let (query_set, query_index) = active_statistics_query.take().unwrap();
+                    raw.end_query(&query_set.raw, query_index);
+                }
+                ArcRenderCommand::PushDebugGroup {
+                    color: _,
+                    offset,
+                    len,
+                } => {
+                    let label = str::from_utf8(&self.base.string_data[*offset..*offset + *len])
+                        .unwrap();
+                    raw.begin_debug_marker(label);
+                }
+                ArcRenderCommand::InsertDebugMarker {
+                    color: _,
+                    offset,
+                    len,
+                } => {
+                    let label = str::from_utf8(&self.base.string_data[*offset..*offset + *len])
+                        .unwrap();
+                    raw.insert_debug_marker(label);
+                }
+                ArcRenderCommand::PopDebugGroup => {
+                    // `finish` already verified that every `PopDebugGroup` is
+                    // paired with a preceding `PushDebugGroup`.
+                    raw.end_debug_marker();
                 }
-                RenderCommand::ExecuteBundle(_)
-                | RenderCommand::SetBlendConstant(_)
-                | RenderCommand::SetStencilReference(_)
-                | RenderCommand::SetViewport { .. }
-                | RenderCommand::SetScissor(_) => unreachable!(),
             }
         }
 
@@ -1122,6 +1826,11 @@ struct State<A: HalApi> {
 
     used_bind_groups: usize,
     pipeline: Option<id::RenderPipelineId>,
+
+    /// The pipeline-statistics query that is currently open, if any, so a
+    /// stray or unbalanced `EndPipelineStatisticsQuery` can be rejected here
+    /// rather than at replay time.
+    active_pipeline_query: Option<(id::QuerySetId, u32)>,
 }
 
 impl<A: HalApi> State<A> {
@@ -1298,16 +2007,49 @@ impl<A: HalApi> State<A> {
 /// Error encountered when finishing recording a render bundle.
 #[derive(Clone, Debug, Error)]
 pub(super) enum RenderBundleErrorInner {
-    #[error("resource is not valid to use with this render bundle because the resource and the bundle come from different devices")]
-    NotValidToUse,
+    #[error("{resource_ident} is not valid to use with this render bundle because it and the bundle come from different devices")]
+    NotValidToUse { resource_ident: String },
     #[error(transparent)]
     Device(#[from] DeviceError),
     #[error(transparent)]
     RenderCommand(RenderCommandError),
+    #[error("bind group '{bind_group_label}' has {actual} dynamic offsets, but its layout expects {expected}")]
+    InvalidDynamicOffsetCount {
+        bind_group_label: String,
+        actual: usize,
+        expected: usize,
+    },
+    #[error("dynamic offset {offset} for bind group '{bind_group_label}' does not meet the required alignment of {alignment} for {limit_name}")]
+    UnalignedBufferOffset {
+        bind_group_label: String,
+        offset: wgt::BufferAddress,
+        limit_name: &'static str,
+        alignment: u32,
+    },
+    #[error("pipeline '{pipeline_label}' is incompatible with this render bundle's targets")]
+    IncompatiblePipelineTargets {
+        pipeline_label: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+    #[error("pipeline '{pipeline_label}' writes to depth/stencil, but this render bundle's depth/stencil attachment is read-only")]
+    IncompatiblePipelineRods { pipeline_label: String },
+    #[error("push constant range for pipeline layout '{pipeline_layout_label}' is invalid")]
+    InvalidPushConstantRange {
+        pipeline_layout_label: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+    #[error("pop_debug_group was called without a matching push_debug_group")]
+    UnbalancedDebugGroupPop,
+    #[error("render bundle finished with an unclosed debug group; every push_debug_group needs a matching pop_debug_group")]
+    UnbalancedDebugGroupPush,
     #[error(transparent)]
     Draw(#[from] DrawError),
     #[error(transparent)]
     MissingDownlevelFlags(#[from] MissingDownlevelFlags),
+    #[error(transparent)]
+    MissingFeatures(#[from] MissingFeatures),
 }
 
 impl<T> From<T> for RenderBundleErrorInner
@@ -1358,7 +2100,7 @@ where
 pub mod bundle_ffi {
     use super::{RenderBundleEncoder, RenderCommand};
     use crate::{id, RawString};
-    use std::{convert::TryInto, slice};
+    use std::{convert::TryInto, num::NonZeroU32, slice};
     use wgt::{BufferAddress, BufferSize, DynamicOffset, IndexFormat};
 
     /// # Safety
@@ -1537,32 +2279,101 @@ pub mod bundle_ffi {
         });
     }
 
+    #[no_mangle]
+    pub extern "C" fn wgpu_render_bundle_multi_draw_indirect(
+        bundle: &mut RenderBundleEncoder,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count: u32,
+        indexed: bool,
+    ) {
+        // `count` is encoded as `Option<NonZeroU32>` with `None` reserved for
+        // the non-multi `wgpu_render_bundle_draw_indirect`/
+        // `wgpu_render_bundle_draw_indexed_indirect` entry points above, so a
+        // caller-supplied `0` here can't be told apart from "not multi-draw"
+        // once converted -- `finish`/`execute` would silently replay it as a
+        // single draw instead of issuing zero draws. Reject it instead of
+        // letting it collapse.
+        assert_ne!(
+            count, 0,
+            "multi_draw_indirect count must be greater than zero"
+        );
+        bundle.base.commands.push(RenderCommand::MultiDrawIndirect {
+            buffer_id,
+            offset,
+            count: NonZeroU32::new(count),
+            indexed,
+        });
+    }
+
+    #[no_mangle]
+    pub extern "C" fn wgpu_render_bundle_multi_draw_indirect_count(
+        bundle: &mut RenderBundleEncoder,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count_buffer_id: id::BufferId,
+        count_buffer_offset: BufferAddress,
+        max_count: u32,
+        indexed: bool,
+    ) {
+        bundle
+            .base
+            .commands
+            .push(RenderCommand::MultiDrawIndirectCount {
+                buffer_id,
+                offset,
+                count_buffer_id,
+                count_buffer_offset,
+                max_count,
+                indexed,
+            });
+    }
+
+    /// Labels the start of a region of the bundle's command stream, so that
+    /// the replayed draws show up grouped under `label` in GPU debuggers
+    /// (RenderDoc, Metal capture) instead of as a flat, unlabeled list.
+    ///
     /// # Safety
     ///
     /// This function is unsafe as there is no guarantee that the given `label`
     /// is a valid null-terminated string.
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_render_bundle_push_debug_group(
-        _bundle: &mut RenderBundleEncoder,
-        _label: RawString,
+        bundle: &mut RenderBundleEncoder,
+        label: RawString,
     ) {
-        //TODO
+        let bytes = std::ffi::CStr::from_ptr(label).to_bytes();
+        bundle.base.string_data.extend_from_slice(bytes);
+        bundle.base.commands.push(RenderCommand::PushDebugGroup {
+            color: 0,
+            len: bytes.len(),
+        });
     }
 
+    /// Closes the debug group opened by the matching
+    /// [`wgpu_render_bundle_push_debug_group`] call.
     #[no_mangle]
-    pub extern "C" fn wgpu_render_bundle_pop_debug_group(_bundle: &mut RenderBundleEncoder) {
-        //TODO
+    pub extern "C" fn wgpu_render_bundle_pop_debug_group(bundle: &mut RenderBundleEncoder) {
+        bundle.base.commands.push(RenderCommand::PopDebugGroup);
     }
 
+    /// Labels a single point in the bundle's command stream for GPU
+    /// debuggers, without opening a region.
+    ///
     /// # Safety
     ///
     /// This function is unsafe as there is no guarantee that the given `label`
     /// is a valid null-terminated string.
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_render_bundle_insert_debug_marker(
-        _bundle: &mut RenderBundleEncoder,
-        _label: RawString,
+        bundle: &mut RenderBundleEncoder,
+        label: RawString,
     ) {
-        //TODO
+        let bytes = std::ffi::CStr::from_ptr(label).to_bytes();
+        bundle.base.string_data.extend_from_slice(bytes);
+        bundle.base.commands.push(RenderCommand::InsertDebugMarker {
+            color: 0,
+            len: bytes.len(),
+        });
     }
 }